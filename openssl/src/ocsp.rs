@@ -7,6 +7,7 @@ use {cvt, cvt_p};
 use asn1::Asn1GeneralizedTimeRef;
 use error::ErrorStack;
 use hash::MessageDigest;
+use pkey::{PKeyRef, Private};
 use stack::StackRef;
 use types::OpenSslTypeRef;
 use x509::store::X509StoreRef;
@@ -25,6 +26,7 @@ bitflags! {
         const FLAG_TRUST_OTHER = ffi::OCSP_TRUSTOTHER,
         const FLAG_RESPID_KEY = ffi::OCSP_RESPID_KEY,
         const FLAG_NO_TIME = ffi::OCSP_NOTIME,
+        const FLAG_NO_SIGS = ffi::OCSP_NOSIGS,
     }
 }
 
@@ -137,6 +139,16 @@ impl<'a> Status<'a> {
 
 type_!(OcspBasicResponse, OcspBasicResponseRef, ffi::OCSP_BASICRESP, ffi::OCSP_BASICRESP_free);
 
+impl OcspBasicResponse {
+    pub fn new() -> Result<OcspBasicResponse, ErrorStack> {
+        unsafe {
+            ffi::init();
+
+            cvt_p(ffi::OCSP_BASICRESP_new()).map(OcspBasicResponse)
+        }
+    }
+}
+
 impl OcspBasicResponseRef {
     /// Verifies the validity of the response.
     ///
@@ -153,6 +165,38 @@ impl OcspBasicResponseRef {
         }
     }
 
+    /// Adds an OCSP nonce extension to the response.
+    ///
+    /// If `nonce` is `None`, a random nonce is generated.
+    pub fn add_nonce(&mut self, nonce: Option<&[u8]>) -> Result<(), ErrorStack> {
+        unsafe {
+            let (ptr, len) = match nonce {
+                Some(nonce) => (nonce.as_ptr() as *mut _, nonce.len() as c_int),
+                None => (ptr::null_mut(), 0),
+            };
+
+            cvt(ffi::OCSP_basic_add1_nonce(self.as_ptr(), ptr, len)).map(|_| ())
+        }
+    }
+
+    /// Compares the nonce of this response against the nonce of `request`.
+    ///
+    /// Returns one of OpenSSL's result codes:
+    ///
+    /// * `1` - the nonce is present and equal in both the request and response.
+    /// * `0` - the nonce is present in both but differs.
+    /// * `-1` - the nonce is present in the request but absent from the response.
+    /// * `3` - the nonce is present in the response but absent from the request.
+    /// * `2` - the nonce is absent from both the request and the response.
+    ///
+    /// Note that a return value of `2` means no replay protection was negotiated at all;
+    /// callers that require a nonce to be present should treat it the same as a mismatch.
+    pub fn check_nonce(&self, request: &OcspRequestRef) -> i32 {
+        unsafe {
+            ffi::OCSP_check_nonce(request.as_ptr(), self.as_ptr()) as i32
+        }
+    }
+
     /// Looks up the status for the specified certificate ID.
     pub fn find_status<'a>(&'a self, id: &OcspCertIdRef) -> Option<Status<'a>> {
         unsafe {
@@ -187,6 +231,115 @@ impl OcspBasicResponseRef {
             }
         }
     }
+
+    /// Returns the number of `SingleResponse` structures contained in the response.
+    pub fn count(&self) -> usize {
+        unsafe {
+            let count = ffi::OCSP_resp_count(self.as_ptr());
+            if count < 0 {
+                0
+            } else {
+                count as usize
+            }
+        }
+    }
+
+    /// Returns the `SingleResponse` at the specified index, if any.
+    pub fn get(&self, idx: usize) -> Option<&OcspSingleRespRef> {
+        unsafe {
+            if idx > c_int::max_value() as usize {
+                return None;
+            }
+
+            let single = ffi::OCSP_resp_get0(self.as_ptr(), idx as c_int);
+            if single.is_null() {
+                None
+            } else {
+                Some(OcspSingleRespRef::from_ptr(single))
+            }
+        }
+    }
+
+    /// Adds a `SingleResponse` to this response recording the status of the certificate
+    /// identified by `id`.
+    ///
+    /// The `revocation_time` parameter is only meaningful when `status` is `CERT_STATUS_REVOKED`.
+    pub fn add_status(&mut self,
+                       id: &OcspCertIdRef,
+                       status: OcspCertStatus,
+                       reason: OcspRevokedStatus,
+                       revocation_time: Option<&Asn1GeneralizedTimeRef>,
+                       this_update: &Asn1GeneralizedTimeRef,
+                       next_update: Option<&Asn1GeneralizedTimeRef>)
+                       -> Result<(), ErrorStack> {
+        unsafe {
+            cvt_p(ffi::OCSP_basic_add1_status(self.as_ptr(),
+                                              id.as_ptr(),
+                                              status.as_raw(),
+                                              reason.as_raw(),
+                                              revocation_time.map(|t| t.as_ptr())
+                                                  .unwrap_or(ptr::null_mut()),
+                                              this_update.as_ptr(),
+                                              next_update.map(|t| t.as_ptr())
+                                                  .unwrap_or(ptr::null_mut())))
+                .map(|_| ())
+        }
+    }
+
+    /// Signs the response with `key`, including `signer` as the responder certificate.
+    ///
+    /// The `certs` parameter contains additional certificates that will be included alongside
+    /// `signer` unless `flags` contains `FLAG_NO_CERTS`.
+    pub fn sign(&mut self,
+                signer: &X509Ref,
+                key: &PKeyRef<Private>,
+                digest: MessageDigest,
+                certs: Option<&StackRef<X509>>,
+                flags: Flag)
+                -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::OCSP_basic_sign(self.as_ptr(),
+                                     signer.as_ptr(),
+                                     key.as_ptr(),
+                                     digest.as_ptr(),
+                                     certs.map(|c| c.as_ptr()).unwrap_or(ptr::null_mut()),
+                                     flags.bits()))
+                .map(|_| ())
+        }
+    }
+}
+
+type_!(OcspSingleResp, OcspSingleRespRef, ffi::OCSP_SINGLERESP, ffi::OCSP_SINGLERESP_free);
+
+impl OcspSingleRespRef {
+    /// Returns the status of this single response.
+    pub fn status(&self) -> Status {
+        unsafe {
+            let mut reason = ffi::OCSP_REVOKED_STATUS_NOSTATUS;
+            let mut revocation_time = ptr::null_mut();
+            let mut this_update = ptr::null_mut();
+            let mut next_update = ptr::null_mut();
+
+            let status = ffi::OCSP_single_get0_status(self.as_ptr(),
+                                                      &mut reason,
+                                                      &mut revocation_time,
+                                                      &mut this_update,
+                                                      &mut next_update);
+
+            let revocation_time = if revocation_time.is_null() {
+                None
+            } else {
+                Some(Asn1GeneralizedTimeRef::from_ptr(revocation_time))
+            };
+            Status {
+                status: OcspCertStatus(status),
+                reason: OcspRevokedStatus(reason),
+                revocation_time: revocation_time,
+                this_update: Asn1GeneralizedTimeRef::from_ptr(this_update),
+                next_update: Asn1GeneralizedTimeRef::from_ptr(next_update),
+            }
+        }
+    }
 }
 
 type_!(OcspCertId, OcspCertIdRef, ffi::OCSP_CERTID, ffi::OCSP_CERTID_free);
@@ -269,6 +422,118 @@ impl OcspRequestRef {
             Ok(OcspOneReqRef::from_ptr_mut(ptr))
         }
     }
+
+    /// Adds an OCSP nonce extension to the request.
+    ///
+    /// If `nonce` is `None`, a random nonce is generated.
+    pub fn add_nonce(&mut self, nonce: Option<&[u8]>) -> Result<(), ErrorStack> {
+        unsafe {
+            let (ptr, len) = match nonce {
+                Some(nonce) => (nonce.as_ptr() as *mut _, nonce.len() as c_int),
+                None => (ptr::null_mut(), 0),
+            };
+
+            cvt(ffi::OCSP_request_add1_nonce(self.as_ptr(), ptr, len)).map(|_| ())
+        }
+    }
+
+    /// Signs the request with `key`, attaching `signer` as the requestor certificate.
+    ///
+    /// The `certs` parameter contains additional certificates that will be included alongside
+    /// `signer` unless `flags` contains `FLAG_NO_CERTS`.
+    pub fn sign(&mut self,
+                signer: &X509Ref,
+                key: &PKeyRef<Private>,
+                digest: MessageDigest,
+                certs: Option<&StackRef<X509>>,
+                flags: Flag)
+                -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::OCSP_request_sign(self.as_ptr(),
+                                       signer.as_ptr(),
+                                       key.as_ptr(),
+                                       digest.as_ptr(),
+                                       certs.map(|c| c.as_ptr()).unwrap_or(ptr::null_mut()),
+                                       flags.bits()))
+                .map(|_| ())
+        }
+    }
 }
 
-type_!(OcspOneReq, OcspOneReqRef, ffi::OCSP_ONEREQ, ffi::OCSP_ONEREQ_free);
\ No newline at end of file
+type_!(OcspOneReq, OcspOneReqRef, ffi::OCSP_ONEREQ, ffi::OCSP_ONEREQ_free);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hash::MessageDigest;
+    use pkey::PKey;
+    use stack::Stack;
+    use x509::X509;
+    use x509::store::X509StoreBuilder;
+
+    fn cert() -> X509 {
+        X509::from_pem(include_bytes!("../test/cert.pem")).unwrap()
+    }
+
+    fn key() -> PKey<Private> {
+        PKey::private_key_from_pem(include_bytes!("../test/key.pem")).unwrap()
+    }
+
+    // Allocates an `ASN1_GENERALIZEDTIME` set to the current time. The caller is responsible
+    // for freeing it with `ffi::ASN1_GENERALIZEDTIME_free`.
+    unsafe fn now() -> *mut ffi::ASN1_GENERALIZEDTIME {
+        let time = ffi::ASN1_GENERALIZEDTIME_new();
+        assert!(!time.is_null());
+        assert!(!ffi::X509_gmtime_adj(time as *mut ffi::ASN1_TIME, 0).is_null());
+        time
+    }
+
+    #[test]
+    fn sign_and_verify_basic_response() {
+        let cert = cert();
+        let key = key();
+        let digest = MessageDigest::sha1();
+
+        let id = OcspCertId::from_cert(digest, &cert, &cert).unwrap();
+
+        let mut basic = OcspBasicResponse::new().unwrap();
+        unsafe {
+            let this_update = now();
+            let result = basic.add_status(&id,
+                                          CERT_STATUS_GOOD,
+                                          REVOKED_STATUS_NO_STATUS,
+                                          None,
+                                          Asn1GeneralizedTimeRef::from_ptr(this_update),
+                                          None);
+            ffi::ASN1_GENERALIZEDTIME_free(this_update);
+            result.unwrap();
+        }
+
+        basic.sign(&cert, &key, digest, None, Flag::empty()).unwrap();
+
+        let mut certs = Stack::new().unwrap();
+        certs.push(cert()).unwrap();
+        let store = X509StoreBuilder::new().unwrap().build();
+
+        // The signer isn't part of a real chain here, so trust it directly via `FLAG_TRUST_OTHER`
+        // rather than building one.
+        basic.verify(&certs, &store, FLAG_TRUST_OTHER).unwrap();
+    }
+
+    #[test]
+    fn sign_request() {
+        let cert = cert();
+        let key = key();
+        let digest = MessageDigest::sha1();
+
+        let mut req = OcspRequest::new().unwrap();
+        let id = OcspCertId::from_cert(digest, &cert, &cert).unwrap();
+        req.add_id(id).unwrap();
+
+        assert_eq!(unsafe { ffi::OCSP_request_is_signed(req.as_ptr()) }, 0);
+
+        req.sign(&cert, &key, digest, None, Flag::empty()).unwrap();
+
+        assert_eq!(unsafe { ffi::OCSP_request_is_signed(req.as_ptr()) }, 1);
+    }
+}
\ No newline at end of file